@@ -12,8 +12,51 @@ use nacos_sdk::api::{
         ServiceInstance,
     },
 };
+use rand::Rng;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// Wraps a credential so `Debug`/`Display` always emit `MASKED`.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct MaskedString(String);
+
+impl MaskedString {
+    pub fn new(value: impl Into<String>) -> Self {
+        MaskedString(value.into())
+    }
+
+    /// Returns the wrapped value; don't pass this to anything that logs it.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for MaskedString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "MASKED")
+    }
+}
+
+impl std::fmt::Display for MaskedString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "MASKED")
+    }
+}
+
+impl From<String> for MaskedString {
+    fn from(value: String) -> Self {
+        MaskedString(value)
+    }
+}
+
+impl From<&str> for MaskedString {
+    fn from(value: &str) -> Self {
+        MaskedString(value.to_string())
+    }
+}
 
 #[derive(Debug)]
 pub struct NacosNamingAndConfigData {
@@ -23,6 +66,117 @@ pub struct NacosNamingAndConfigData {
     state: RwLock<NamingState>,
 
     pub event_listener: Arc<NacosEventListener>,
+
+    load_balance: RwLock<Arc<dyn LoadBalance>>,
+
+    service_stats: DashMap<String, ServiceStats>,
+}
+
+/// Request/error counters for one registered service.
+#[derive(Debug, Clone, Default)]
+pub struct ServiceStats {
+    pub num_requests: u64,
+    pub num_errors: u64,
+    pub last_error: Option<String>,
+    pub processing_time: std::time::Duration,
+    pub average_processing_time: std::time::Duration,
+}
+
+/// Folds one request's outcome into `stats`' running counters.
+fn update_service_stats(
+    stats: &mut ServiceStats,
+    elapsed: std::time::Duration,
+    result: std::result::Result<(), String>,
+) {
+    stats.num_requests += 1;
+    stats.processing_time += elapsed;
+    stats.average_processing_time = stats.processing_time / stats.num_requests as u32;
+    if let Err(err) = result {
+        stats.num_errors += 1;
+        stats.last_error = Some(err);
+    }
+}
+
+/// Strategy for picking one instance out of several candidates.
+pub trait LoadBalance: Send + Sync {
+    /// Picks one instance out of the already-filtered `instances` slice.
+    fn select(&self, service_name: &str, instances: &[ServiceInstance]) -> Option<ServiceInstance>;
+}
+
+impl std::fmt::Debug for dyn LoadBalance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<load balance strategy>")
+    }
+}
+
+/// Picks a uniformly random instance on every call.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RandomLoadBalance;
+
+impl LoadBalance for RandomLoadBalance {
+    fn select(
+        &self,
+        _service_name: &str,
+        instances: &[ServiceInstance],
+    ) -> Option<ServiceInstance> {
+        if instances.is_empty() {
+            return None;
+        }
+        let idx = rand::thread_rng().gen_range(0..instances.len());
+        Some(instances[idx].clone())
+    }
+}
+
+/// Cycles through instances in order, keeping a per-service cursor so
+/// repeated calls for the same service advance rather than restart.
+#[derive(Debug, Default)]
+pub struct RoundRobinLoadBalance {
+    cursors: DashMap<String, AtomicUsize>,
+}
+
+impl LoadBalance for RoundRobinLoadBalance {
+    fn select(&self, service_name: &str, instances: &[ServiceInstance]) -> Option<ServiceInstance> {
+        if instances.is_empty() {
+            return None;
+        }
+        let cursor = self
+            .cursors
+            .entry(service_name.to_string())
+            .or_insert_with(|| AtomicUsize::new(0));
+        let idx = cursor.fetch_add(1, Ordering::Relaxed) % instances.len();
+        Some(instances[idx].clone())
+    }
+}
+
+/// Draws an instance with probability proportional to its `weight`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WeightedRandomLoadBalance;
+
+impl LoadBalance for WeightedRandomLoadBalance {
+    fn select(
+        &self,
+        _service_name: &str,
+        instances: &[ServiceInstance],
+    ) -> Option<ServiceInstance> {
+        if instances.is_empty() {
+            return None;
+        }
+        let weights_valid = instances
+            .iter()
+            .all(|inst| inst.weight.is_finite() && inst.weight >= 0.0);
+        let total_weight: f64 = instances.iter().map(|inst| inst.weight).sum();
+        if !weights_valid || total_weight <= 0.0 {
+            return instances.first().cloned();
+        }
+        let mut draw = rand::thread_rng().gen_range(0.0..total_weight);
+        for inst in instances {
+            if draw < inst.weight {
+                return Some(inst.clone());
+            }
+            draw -= inst.weight;
+        }
+        instances.last().cloned()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -31,11 +185,102 @@ pub struct NacosEventListener {
     pub sub_svc_change_sender: async_broadcast::Sender<Arc<NamingChangeEvent>>,
     pub sub_svc_change_receiver: async_broadcast::Receiver<Arc<NamingChangeEvent>>,
 
-    pub config_data_map: DashMap<String, ConfigResponse>,
+    pub config_data_map: DashMap<(String, String), ConfigResponse>,
     pub config_change_sender: async_broadcast::Sender<ConfigResponse>,
     pub config_change_receiver: async_broadcast::Receiver<ConfigResponse>,
 }
 
+/// A structured diff of a typed config value across reloads, broadcast by
+/// [`NacosNamingAndConfigData::watch_typed_config`].
+#[derive(Debug, Clone)]
+pub struct ConfigChange<T> {
+    pub old: Option<T>,
+    pub new: T,
+    pub changed_fields: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConfigFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+/// Picks a format from the content type, falling back to sniffing the
+/// content itself when it's missing or unrecognized.
+fn detect_format(content_type: &str, content: &str) -> ConfigFormat {
+    let content_type = content_type.to_lowercase();
+    if content_type.contains("json") {
+        return ConfigFormat::Json;
+    }
+    if content_type.contains("yaml") || content_type.contains("yml") {
+        return ConfigFormat::Yaml;
+    }
+    if content_type.contains("toml") {
+        return ConfigFormat::Toml;
+    }
+    sniff_format(content)
+}
+
+/// Guesses a format from the raw content (leading `{`/`[` => JSON, leading
+/// `---`/`key:` => YAML, otherwise TOML).
+fn sniff_format(content: &str) -> ConfigFormat {
+    let trimmed = content.trim_start();
+    if trimmed.starts_with('{') || trimmed.starts_with('[') {
+        return ConfigFormat::Json;
+    }
+    if let Some(first_line) = trimmed.lines().next() {
+        let first_line = first_line.trim();
+        if first_line.starts_with("---") || is_yaml_key_line(first_line) {
+            return ConfigFormat::Yaml;
+        }
+    }
+    ConfigFormat::Toml
+}
+
+fn is_yaml_key_line(line: &str) -> bool {
+    !line.starts_with('#')
+        && line
+            .split_once(':')
+            .map_or(false, |(key, _)| !key.trim().is_empty() && !key.contains('='))
+}
+
+fn deserialize_content<T: DeserializeOwned>(content: &str, format: ConfigFormat) -> Result<T> {
+    match format {
+        ConfigFormat::Json => serde_json::from_str(content)
+            .map_err(|e| anyhow!("failed to parse config as json: {}", e)),
+        ConfigFormat::Yaml => serde_yaml::from_str(content)
+            .map_err(|e| anyhow!("failed to parse config as yaml: {}", e)),
+        ConfigFormat::Toml => {
+            toml::from_str(content).map_err(|e| anyhow!("failed to parse config as toml: {}", e))
+        }
+    }
+}
+
+/// Returns the top-level field names that differ between `old` and `new`,
+/// sorted and deduplicated.
+fn diff_fields<T: Serialize>(old: &T, new: &T) -> Vec<String> {
+    let (Ok(old_value), Ok(new_value)) = (serde_json::to_value(old), serde_json::to_value(new))
+    else {
+        return Vec::new();
+    };
+    let (serde_json::Value::Object(old_map), serde_json::Value::Object(new_map)) =
+        (old_value, new_value)
+    else {
+        return Vec::new();
+    };
+
+    let mut changed: Vec<String> = old_map
+        .keys()
+        .chain(new_map.keys())
+        .filter(|key| old_map.get(*key) != new_map.get(*key))
+        .cloned()
+        .collect();
+    changed.sort();
+    changed.dedup();
+    changed
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct NamingState {
     pub service_name: String,
@@ -63,8 +308,8 @@ impl NacosNamingAndConfigData {
         server_addr: String,
         namespace: String,
         app_name: String,
-        user_name: Option<String>,
-        password: Option<String>,
+        user_name: Option<MaskedString>,
+        password: Option<MaskedString>,
     ) -> Result<Self> {
         let mut client_props = ClientProps::new()
             // eg. "127.0.0.1:8848"
@@ -78,14 +323,14 @@ impl NacosNamingAndConfigData {
 
         let mut enable_http_login = false;
         if let Some(user_name) = user_name {
-            if !user_name.is_empty() {
-                client_props = client_props.auth_username(user_name);
+            if !user_name.expose().is_empty() {
+                client_props = client_props.auth_username(user_name.expose().to_string());
                 enable_http_login = true;
             }
         }
         if let Some(password) = password {
-            if !password.is_empty() {
-                client_props = client_props.auth_password(password);
+            if !password.expose().is_empty() {
+                client_props = client_props.auth_password(password.expose().to_string());
                 enable_http_login = true;
             }
         }
@@ -126,9 +371,114 @@ impl NacosNamingAndConfigData {
                 service_instance: Vec::new(),
             }),
             event_listener: Arc::new(nel),
+            load_balance: RwLock::new(Arc::new(RandomLoadBalance)),
+            service_stats: DashMap::default(),
         })
     }
 
+    /// Records one request's outcome against `service_name`'s running stats.
+    pub fn record_request(
+        &self,
+        service_name: &str,
+        elapsed: std::time::Duration,
+        result: std::result::Result<(), String>,
+    ) {
+        let mut stats = self
+            .service_stats
+            .entry(service_name.to_string())
+            .or_default();
+        update_service_stats(&mut stats, elapsed, result);
+    }
+
+    /// Returns a snapshot of the current per-service request/error counters.
+    pub fn stats(&self) -> HashMap<String, ServiceStats> {
+        self.service_stats
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect()
+    }
+
+    /// Spawns a background task that, every `interval`, runs `health_check`
+    /// and re-registers the current instances with `healthy` flipped to
+    /// match, rather than waiting on Nacos' TTL expiry.
+    pub fn start_heartbeat(
+        self: &Arc<Self>,
+        interval: std::time::Duration,
+        health_check: impl Fn() -> bool + Send + Sync + 'static,
+    ) -> tokio::task::JoinHandle<()> {
+        let this = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+
+                let healthy = health_check();
+                let state = this.get_state();
+                for inst in state.service_instance {
+                    let mut updated = inst;
+                    updated.healthy = healthy;
+                    if let Err(err) = this
+                        .naming
+                        .register_instance(
+                            state.service_name.clone(),
+                            state.group_name.clone(),
+                            updated,
+                        )
+                        .await
+                    {
+                        tracing::error!(
+                            "heartbeat: failed to re-assert health for {}: {}",
+                            state.service_name,
+                            err
+                        );
+                    }
+                }
+            }
+        })
+    }
+
+    /// Replace the default load balancing strategy used by `select_instance`.
+    pub fn set_load_balance(&self, load_balance: Arc<dyn LoadBalance>) {
+        *self.load_balance.write().unwrap() = load_balance;
+    }
+
+    /// Pick one healthy, enabled instance of `service_name` using the
+    /// configured load balancing strategy.
+    ///
+    /// `sub_svc_map` is keyed by service name only (see `subscribe_service`),
+    /// so there is no per-group instance list to select from yet; this takes
+    /// no `group_name` parameter rather than silently ignoring one.
+    pub fn select_instance(&self, service_name: &str) -> Result<ServiceInstance> {
+        let load_balance = self.load_balance.read().unwrap().clone();
+        self.select_instance_with(service_name, load_balance.as_ref())
+    }
+
+    /// Like [`Self::select_instance`] but uses `load_balance` for this call
+    /// only, ignoring the strategy configured via [`Self::set_load_balance`].
+    pub fn select_instance_with(
+        &self,
+        service_name: &str,
+        load_balance: &dyn LoadBalance,
+    ) -> Result<ServiceInstance> {
+        let candidates: Vec<ServiceInstance> = self
+            .event_listener
+            .sub_svc_map
+            .get(service_name)
+            .map(|entry| {
+                entry
+                    .value()
+                    .iter()
+                    .filter(|inst| inst.healthy && inst.enabled)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        load_balance
+            .select(service_name, &candidates)
+            .ok_or_else(|| anyhow!("no healthy instance available for service {}", service_name))
+    }
+
     /// register self to nacos
     pub async fn register_service(
         &self,
@@ -268,6 +618,242 @@ impl NacosNamingAndConfigData {
             Err(err) => Err(anyhow!("failed to get config: {}", err)),
         }
     }
+
+    /// Like [`Self::get_config`] but deserializes the content into `T`,
+    /// autodetecting the format.
+    pub async fn get_typed_config<T: DeserializeOwned>(
+        &self,
+        data_id: String,
+        group_name: String,
+    ) -> Result<T> {
+        let resp = self
+            .config
+            .get_config(data_id, group_name)
+            .await
+            .map_err(|err| anyhow!("failed to get config: {}", err))?;
+        let format = detect_format(resp.content_type(), resp.content());
+        deserialize_content(resp.content(), format)
+    }
+
+    /// Subscribes to `data_id`/`group_name` and returns a receiver of
+    /// typed config changes, each carrying the old/new value and the
+    /// fields that differ between them.
+    pub async fn watch_typed_config<T>(
+        &self,
+        data_id: String,
+        group_name: String,
+    ) -> Result<async_broadcast::Receiver<ConfigChange<T>>>
+    where
+        T: DeserializeOwned + Serialize + Clone + Send + Sync + 'static,
+    {
+        self.add_default_config_listener(data_id.clone(), group_name.clone())
+            .await?;
+
+        let initial = self
+            .get_typed_config::<T>(data_id.clone(), group_name.clone())
+            .await
+            .ok();
+
+        let (mut change_sender, change_receiver) = async_broadcast::broadcast(16);
+        change_sender.set_overflow(true);
+
+        let mut config_changes = self.event_listener.config_change_receiver.clone();
+        tokio::spawn(async move {
+            let mut previous = initial;
+            while let Ok(resp) = config_changes.recv().await {
+                if resp.data_id().as_str() != data_id.as_str()
+                    || resp.group().as_str() != group_name.as_str()
+                {
+                    continue;
+                }
+
+                let format = detect_format(resp.content_type(), resp.content());
+                let Ok(new_value) = deserialize_content::<T>(resp.content(), format) else {
+                    tracing::warn!(
+                        "failed to deserialize typed config {}@{}",
+                        data_id,
+                        group_name
+                    );
+                    continue;
+                };
+
+                let changed_fields = previous
+                    .as_ref()
+                    .map(|old| diff_fields(old, &new_value))
+                    .unwrap_or_default();
+                let change = ConfigChange {
+                    old: previous.clone(),
+                    new: new_value.clone(),
+                    changed_fields,
+                };
+                previous = Some(new_value);
+                let _ = change_sender.try_broadcast(change);
+            }
+        });
+
+        Ok(change_receiver)
+    }
+
+    /// Publishes `content` to `data_id`/`group_name`, creating or
+    /// overwriting it.
+    pub async fn publish_config(
+        &self,
+        data_id: String,
+        group_name: String,
+        content: String,
+        content_type: Option<String>,
+    ) -> Result<bool> {
+        self.config
+            .publish_config(data_id, group_name, content, content_type)
+            .await
+            .map_err(|err| anyhow!("failed to publish config: {}", err))
+    }
+
+    /// Publishes `content` to `data_id`/`group_name` only if it still
+    /// matches the last-known `md5` we cached for it, preventing a blind
+    /// overwrite of someone else's concurrent change.
+    pub async fn publish_config_cas(
+        &self,
+        data_id: String,
+        group_name: String,
+        content: String,
+        content_type: Option<String>,
+    ) -> Result<bool> {
+        let cas_md5 = self
+            .event_listener
+            .config_data_map
+            .get(&(data_id.clone(), group_name.clone()))
+            .map(|resp| resp.md5().clone())
+            .ok_or_else(|| {
+                anyhow!(
+                    "no cached config for {}@{} to compare-and-swap against",
+                    data_id,
+                    group_name
+                )
+            })?;
+
+        self.config
+            .publish_config_cas(data_id, group_name, content, content_type, cas_md5)
+            .await
+            .map_err(|err| anyhow!("failed to publish config (cas): {}", err))
+    }
+
+    /// Deletes the config at `data_id`/`group_name`.
+    pub async fn remove_config(&self, data_id: String, group_name: String) -> Result<bool> {
+        self.config
+            .remove_config(data_id, group_name)
+            .await
+            .map_err(|err| anyhow!("failed to remove config: {}", err))
+    }
+
+    /// Filters `service_name`'s known instances to those whose metadata
+    /// matches every entry in `filters` (exact, glob, or range); a missing
+    /// metadata key is a non-match.
+    pub fn filter_instances(
+        &self,
+        service_name: &str,
+        filters: HashMap<String, String>,
+    ) -> Vec<ServiceInstance> {
+        let instances = self
+            .event_listener
+            .sub_svc_map
+            .get(service_name)
+            .map(|entry| entry.value().clone())
+            .unwrap_or_default();
+
+        instances
+            .into_iter()
+            .filter(|inst| {
+                filters
+                    .iter()
+                    .all(|(key, want)| match inst.metadata.get(key) {
+                        Some(got) => metadata_matches(want, got),
+                        None => false,
+                    })
+            })
+            .collect()
+    }
+}
+
+/// True when `value` has no glob metacharacters.
+fn is_concrete_str(value: &str) -> bool {
+    !is_wildcard_str(value)
+}
+
+/// True when `value` contains a glob metacharacter (`*`/`?`).
+fn is_wildcard_str(value: &str) -> bool {
+    value.contains('*') || value.contains('?')
+}
+
+/// True when `value` looks like a `[min,max]` or `min~max` range.
+fn is_range_str(value: &str) -> bool {
+    (value.starts_with('[') && value.ends_with(']') && value.contains(',')) || value.contains('~')
+}
+
+/// Dispatches to exact, glob, or range matching depending on the filter value's shape.
+fn metadata_matches(filter_value: &str, candidate: &str) -> bool {
+    if is_wildcard_str(filter_value) {
+        return match_wildcard(filter_value, candidate);
+    }
+    debug_assert!(is_concrete_str(filter_value));
+    if is_range_str(filter_value) {
+        return match_range(filter_value, candidate);
+    }
+    filter_value == candidate
+}
+
+/// Matches `value` against a glob `pattern` (`*` = any run, `?` = one char).
+fn match_wildcard(pattern: &str, value: &str) -> bool {
+    let pattern = pattern.as_bytes();
+    let value = value.as_bytes();
+
+    let (mut p, mut s) = (0usize, 0usize);
+    let mut star: Option<(usize, usize)> = None;
+
+    while s < value.len() {
+        if p < pattern.len() && (pattern[p] == b'?' || pattern[p] == value[s]) {
+            p += 1;
+            s += 1;
+        } else if p < pattern.len() && pattern[p] == b'*' {
+            star = Some((p, s));
+            p += 1;
+        } else if let Some((star_p, star_s)) = star {
+            p = star_p + 1;
+            s = star_s + 1;
+            star = Some((star_p, s));
+        } else {
+            return false;
+        }
+    }
+    while p < pattern.len() && pattern[p] == b'*' {
+        p += 1;
+    }
+    p == pattern.len()
+}
+
+/// Parses a `[min,max]`/`min~max` range and checks whether `value` falls
+/// within it; an empty bound is unbounded.
+fn match_range(range: &str, value: &str) -> bool {
+    let trimmed = range.trim().trim_start_matches('[').trim_end_matches(']');
+    let Some((min, max)) = trimmed.split_once(',').or_else(|| trimmed.split_once('~')) else {
+        return false;
+    };
+    let (min, max) = (min.trim(), max.trim());
+
+    let above_min = min.is_empty() || compare_versions(value, min) != std::cmp::Ordering::Less;
+    let below_max = max.is_empty() || compare_versions(value, max) != std::cmp::Ordering::Greater;
+    above_min && below_max
+}
+
+/// Compares two dotted version tuples component-wise (also covers plain
+/// single-component integers).
+fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    let parse = |s: &str| -> Vec<i64> {
+        s.split('.')
+            .map(|part| part.trim().parse::<i64>().unwrap_or(0))
+            .collect()
+    };
+    parse(a).cmp(&parse(b))
 }
 
 impl NamingEventListener for NacosEventListener {
@@ -283,9 +869,255 @@ impl NamingEventListener for NacosEventListener {
 impl ConfigChangeListener for NacosEventListener {
     fn notify(&self, config_resp: ConfigResponse) {
         tracing::debug!("config change event={:?}", config_resp.clone());
-        self.config_data_map
-            .insert(config_resp.data_id().clone(), config_resp.clone());
+        self.config_data_map.insert(
+            (config_resp.data_id().clone(), config_resp.group().clone()),
+            config_resp.clone(),
+        );
 
         let _ = self.config_change_sender.try_broadcast(config_resp);
     }
 }
+
+/// Parameters for constructing the process-wide shared [`NacosNamingAndConfigData`].
+#[derive(Clone, Debug)]
+pub struct NacosClientConfig {
+    pub server_addr: String,
+    pub namespace: String,
+    pub app_name: String,
+    pub user_name: Option<MaskedString>,
+    pub password: Option<MaskedString>,
+}
+
+static GLOBAL_NACOS: OnceLock<Arc<NacosNamingAndConfigData>> = OnceLock::new();
+static GLOBAL_NACOS_INIT: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+/// Returns the process-wide shared naming+config handle, building it from
+/// `config` on the first call. Later calls ignore `config` and return the
+/// handle the first caller created.
+pub fn global(config: NacosClientConfig) -> Result<&'static Arc<NacosNamingAndConfigData>> {
+    if let Some(existing) = GLOBAL_NACOS.get() {
+        return Ok(existing);
+    }
+
+    // Double-checked locking: only the thread holding the lock constructs a
+    // client, so two concurrent first callers can't each stand up their own
+    // naming/config SDK client and leak the loser's.
+    let _guard = GLOBAL_NACOS_INIT.lock().unwrap();
+    if let Some(existing) = GLOBAL_NACOS.get() {
+        return Ok(existing);
+    }
+
+    let data = Arc::new(NacosNamingAndConfigData::new(
+        config.server_addr,
+        config.namespace,
+        config.app_name,
+        config.user_name,
+        config.password,
+    )?);
+    let _ = GLOBAL_NACOS.set(data);
+    Ok(GLOBAL_NACOS.get().expect("just initialized above"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instance(weight: f64, healthy: bool, enabled: bool) -> ServiceInstance {
+        ServiceInstance {
+            weight,
+            healthy,
+            enabled,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn masked_string_never_leaks_the_secret() {
+        let masked = MaskedString::new("super-secret-password");
+        assert_eq!(format!("{:?}", masked), "MASKED");
+        assert_eq!(format!("{}", masked), "MASKED");
+        assert!(!format!("{:?}", masked).contains("super-secret-password"));
+        assert!(!format!("{}", masked).contains("super-secret-password"));
+        assert_eq!(masked.expose(), "super-secret-password");
+    }
+
+    #[test]
+    fn service_stats_track_requests_and_errors() {
+        let mut stats = ServiceStats::default();
+        update_service_stats(&mut stats, std::time::Duration::from_millis(100), Ok(()));
+        update_service_stats(
+            &mut stats,
+            std::time::Duration::from_millis(300),
+            Err("boom".to_string()),
+        );
+
+        assert_eq!(stats.num_requests, 2);
+        assert_eq!(stats.num_errors, 1);
+        assert_eq!(stats.last_error, Some("boom".to_string()));
+        assert_eq!(
+            stats.average_processing_time,
+            std::time::Duration::from_millis(200)
+        );
+    }
+
+    #[test]
+    fn round_robin_cycles_and_wraps() {
+        let lb = RoundRobinLoadBalance::default();
+        let instances = vec![
+            instance(1.0, true, true),
+            instance(2.0, true, true),
+            instance(3.0, true, true),
+        ];
+        let picks: Vec<usize> = (0..4)
+            .map(|_| {
+                let picked = lb.select("svc", &instances).unwrap();
+                instances
+                    .iter()
+                    .position(|i| i.weight == picked.weight)
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(picks, vec![0, 1, 2, 0]);
+    }
+
+    #[test]
+    fn round_robin_on_empty_is_none() {
+        let lb = RoundRobinLoadBalance::default();
+        assert!(lb.select("svc", &[]).is_none());
+    }
+
+    #[test]
+    fn weighted_random_never_picks_zero_weight_when_others_have_weight() {
+        let lb = WeightedRandomLoadBalance;
+        let instances = vec![instance(0.0, true, true), instance(5.0, true, true)];
+        for _ in 0..50 {
+            let picked = lb.select("svc", &instances).unwrap();
+            assert_eq!(picked.weight, 5.0);
+        }
+    }
+
+    #[test]
+    fn weighted_random_falls_back_to_first_when_all_weights_zero() {
+        let lb = WeightedRandomLoadBalance;
+        let instances = vec![instance(0.0, true, true), instance(0.0, true, true)];
+        let picked = lb.select("svc", &instances).unwrap();
+        assert_eq!(picked.weight, instances[0].weight);
+    }
+
+    #[test]
+    fn weighted_random_falls_back_instead_of_panicking_on_nan_weight() {
+        let lb = WeightedRandomLoadBalance;
+        let instances = vec![instance(f64::NAN, true, true), instance(5.0, true, true)];
+        let picked = lb.select("svc", &instances).unwrap();
+        assert_eq!(
+            picked.weight.total_cmp(&instances[0].weight),
+            std::cmp::Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn random_on_empty_is_none() {
+        let lb = RandomLoadBalance;
+        assert!(lb.select("svc", &[]).is_none());
+    }
+
+    #[test]
+    fn wildcard_matches_star_and_question_mark() {
+        assert!(match_wildcard("1.*", "1.2.3"));
+        assert!(match_wildcard("1.?.3", "1.2.3"));
+        assert!(!match_wildcard("1.?.3", "1.22.3"));
+        assert!(match_wildcard("*", ""));
+        assert!(match_wildcard("a*b", "ab"));
+        assert!(!match_wildcard("a*b", "ac"));
+    }
+
+    #[test]
+    fn wildcard_matching_does_not_blow_up_on_many_stars() {
+        // Shape that causes exponential blowup in naive backtracking
+        // recursion; the two-pointer matcher only backtracks to the most
+        // recent `*`, so its work stays bounded by pattern times value length.
+        let value = "a".repeat(40);
+        let pattern = "a*".repeat(40) + "b";
+        assert!(!match_wildcard(&pattern, &value));
+    }
+
+    #[test]
+    fn range_matches_inclusive_bounds() {
+        assert!(match_range("[1,5]", "1"));
+        assert!(match_range("[1,5]", "5"));
+        assert!(match_range("[1,5]", "3"));
+        assert!(!match_range("[1,5]", "6"));
+        assert!(match_range("1~5", "5"));
+    }
+
+    #[test]
+    fn range_with_empty_bound_is_unbounded_on_that_side() {
+        assert!(match_range("[,5]", "-100"));
+        assert!(match_range("[1,]", "10000"));
+    }
+
+    #[test]
+    fn range_compares_dotted_versions_component_wise() {
+        assert!(match_range("[1.2.0,1.10.0]", "1.9.9"));
+        assert!(!match_range("[1.2.0,1.9.0]", "1.10.0"));
+    }
+
+    #[test]
+    fn metadata_matches_dispatches_by_filter_shape() {
+        assert!(metadata_matches("1.0", "1.0"));
+        assert!(!metadata_matches("1.0", "1.1"));
+        assert!(metadata_matches("1.*", "1.5"));
+        assert!(metadata_matches("[1,5]", "3"));
+        assert!(!metadata_matches("[1,5]", "9"));
+    }
+
+    #[test]
+    fn detect_format_prefers_content_type() {
+        assert_eq!(
+            detect_format("application/json", "ignored"),
+            ConfigFormat::Json
+        );
+        assert_eq!(detect_format("text/yaml", "ignored"), ConfigFormat::Yaml);
+        assert_eq!(
+            detect_format("application/toml", "ignored"),
+            ConfigFormat::Toml
+        );
+    }
+
+    #[test]
+    fn detect_format_sniffs_when_content_type_unrecognized() {
+        assert_eq!(detect_format("", r#"{"a":1}"#), ConfigFormat::Json);
+        assert_eq!(detect_format("text/plain", "---\na: 1"), ConfigFormat::Yaml);
+        assert_eq!(detect_format("text/plain", "a: 1"), ConfigFormat::Yaml);
+        assert_eq!(detect_format("text/plain", "a = 1"), ConfigFormat::Toml);
+    }
+
+    #[derive(Serialize, serde::Deserialize, Clone)]
+    struct SampleConfig {
+        a: i32,
+        b: String,
+    }
+
+    #[test]
+    fn diff_fields_reports_only_changed_keys() {
+        let old = SampleConfig {
+            a: 1,
+            b: "x".to_string(),
+        };
+        let new = SampleConfig {
+            a: 1,
+            b: "y".to_string(),
+        };
+        assert_eq!(diff_fields(&old, &new), vec!["b".to_string()]);
+    }
+
+    #[test]
+    fn diff_fields_is_empty_when_nothing_changed() {
+        let old = SampleConfig {
+            a: 1,
+            b: "x".to_string(),
+        };
+        let new = old.clone();
+        assert!(diff_fields(&old, &new).is_empty());
+    }
+}